@@ -1,7 +1,47 @@
 #![forbid(unsafe_code)]
-use std::collections::BTreeSet;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 
-use crate::{create_signal, queue_microtask, ReadSignal, ResourceId, Scope, WriteSignal};
+// `std::time::Instant` panics on `wasm32-unknown-unknown`; `instant::Instant` rides the same
+// `performance.now()` path `set_timeout` uses there and falls back to `std::time::Instant`
+// elsewhere.
+use instant::Instant;
+
+use crate::{
+    create_effect, create_signal, on_cleanup, queue_microtask, set_timeout, store_value,
+    ReadSignal, ResourceId, Scope, StoredValue, WriteSignal,
+};
+
+thread_local! {
+    /// Every [`SuspenseContext`] currently mounted, so a devtools overlay or streaming renderer
+    /// can enumerate all active boundaries (and their still-pending resources) rather than only
+    /// the one it happens to be subscribed to.
+    static SUSPENSE_REGISTRY: RefCell<Vec<SuspenseContext>> = RefCell::new(Vec::new());
+}
+
+/// Returns every currently-mounted [`SuspenseContext`] together with its still-pending resource
+/// ids. See [`SUSPENSE_REGISTRY`].
+pub fn all_active_suspense_contexts() -> Vec<(SuspenseContext, BTreeSet<ResourceId>)> {
+    SUSPENSE_REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .iter()
+            .map(|context| (*context, context.pending_resource_ids.get()))
+            .collect()
+    })
+}
+
+/// The maximum number of entries kept in [`SuspenseContext::recent_resolutions`].
+const RECENT_RESOLUTIONS_CAPACITY: usize = 32;
+
+/// The maximum number of cancelled-but-not-yet-decremented ids tracked at once. Bounds the cost
+/// of a resource whose `decrement` never arrives because it was aborted outright.
+const CANCELLED_CAPACITY: usize = 128;
 
 /// Tracks [Resource](crate::Resource)s that are read under a suspense context,
 /// i.e., within a [`Suspense`](https://docs.rs/leptos_core/latest/leptos_core/fn.Suspense.html) component.
@@ -13,6 +53,39 @@ pub struct SuspenseContext {
     /// the resource ids of the currently pending resources
     pub pending_resource_ids: ReadSignal<BTreeSet<ResourceId>>,
     set_pending_resource_ids: WriteSignal<BTreeSet<ResourceId>>,
+    /// `true` if the boundary has been pending for longer than the deadline passed to
+    /// [`increment_with_deadline`](SuspenseContext::increment_with_deadline).
+    pub timed_out: ReadSignal<bool>,
+    set_timed_out: WriteSignal<bool>,
+    /// `true` from the moment a deadline timer from [`increment_with_deadline`](SuspenseContext::increment_with_deadline)
+    /// is armed until the pending count next returns to `0` (it stays `true` after the timer has
+    /// fired), so a burst of several resources starting in the same tick only arms one timer.
+    deadline_armed: ReadSignal<bool>,
+    set_deadline_armed: WriteSignal<bool>,
+    /// when each currently-pending resource started, for computing resolution durations
+    pending_since: ReadSignal<HashMap<ResourceId, Instant>>,
+    set_pending_since: WriteSignal<HashMap<ResourceId, Instant>>,
+    /// a rolling log of the most recently resolved resources and how long they took, for
+    /// devtools/logging layers to inspect.
+    pub recent_resolutions: ReadSignal<VecDeque<(ResourceId, Duration)>>,
+    set_recent_resolutions: WriteSignal<VecDeque<(ResourceId, Duration)>>,
+    /// ids that were cancelled before a still-outstanding `increment`/`decrement` for them
+    /// landed, so those deferred calls can be ignored instead of double-counting. Only
+    /// `decrement` consumes an entry (it's always the terminal event for a given id), so a
+    /// cancelled id stays suppressed regardless of which of the two microtasks drains first.
+    /// Capped like [`recent_resolutions`](SuspenseContext::recent_resolutions) so an id whose
+    /// `decrement` never arrives (its resource was aborted outright) doesn't leak forever.
+    cancelled: ReadSignal<VecDeque<ResourceId>>,
+    set_cancelled: WriteSignal<VecDeque<ResourceId>>,
+    /// the reactive scope that owns this context, needed to register the effect backing
+    /// [`on_ready`](SuspenseContext::on_ready)
+    cx: Scope,
+    /// the enclosing suspense context, if this one is nested, so resolution events can bubble
+    /// up for a streaming renderer that watches the outermost boundary
+    parent: Option<StoredValue<SuspenseContext>>,
+    /// callbacks invoked with the id of each resource that resolves in this context, for an
+    /// out-of-order streaming renderer to flush that resource's fragment as soon as it's ready
+    subscribers: StoredValue<Vec<Rc<dyn Fn(ResourceId)>>>,
 }
 
 impl std::hash::Hash for SuspenseContext {
@@ -30,16 +103,79 @@ impl PartialEq for SuspenseContext {
 impl Eq for SuspenseContext {}
 
 impl SuspenseContext {
-    /// Creates an empty suspense context.
+    /// Creates an empty suspense context. If this is called while another `SuspenseContext` is
+    /// already provided on `cx` (i.e. this `Suspense` is nested inside another one), that
+    /// context is automatically linked as the parent — see
+    /// [`new_with_parent`](SuspenseContext::new_with_parent).
     pub fn new(cx: Scope) -> Self {
+        let parent = cx.use_context::<SuspenseContext>();
+        Self::new_with_parent(cx, parent)
+    }
+
+    /// Creates an empty suspense context nested under `parent`. Resource resolution events
+    /// subscribed to via [`subscribe`](SuspenseContext::subscribe) bubble up to `parent` as
+    /// well, so an out-of-order streaming renderer can watch a single outermost boundary and
+    /// still hear about every nested one.
+    pub fn new_with_parent(cx: Scope, parent: Option<SuspenseContext>) -> Self {
         let (pending_resources, set_pending_resources) = create_signal(cx, 0);
         let (pending_resource_ids, set_pending_resource_ids) =
             create_signal(cx, Default::default());
-        Self {
+        let (timed_out, set_timed_out) = create_signal(cx, false);
+        let (deadline_armed, set_deadline_armed) = create_signal(cx, false);
+        let (pending_since, set_pending_since) = create_signal(cx, Default::default());
+        let (recent_resolutions, set_recent_resolutions) = create_signal(cx, Default::default());
+        let (cancelled, set_cancelled) = create_signal(cx, Default::default());
+        let this = Self {
             pending_resources,
             set_pending_resources,
             pending_resource_ids,
             set_pending_resource_ids,
+            timed_out,
+            set_timed_out,
+            deadline_armed,
+            set_deadline_armed,
+            pending_since,
+            set_pending_since,
+            recent_resolutions,
+            set_recent_resolutions,
+            cancelled,
+            set_cancelled,
+            cx,
+            parent: parent.map(|parent| store_value(cx, parent)),
+            subscribers: store_value(cx, Vec::new()),
+        };
+
+        SUSPENSE_REGISTRY.with(|registry| registry.borrow_mut().push(this));
+        let registry_id = this.pending_resources.id;
+        on_cleanup(cx, move || {
+            SUSPENSE_REGISTRY.with(|registry| {
+                registry
+                    .borrow_mut()
+                    .retain(|context| context.pending_resources.id != registry_id);
+            });
+        });
+
+        this
+    }
+
+    /// Registers `f` to be called with the [`ResourceId`] of each resource that resolves in this
+    /// context (but not resources that were [cancelled](SuspenseContext::cancel)). The event
+    /// also propagates to the parent context, if any, so a streaming renderer subscribed at the
+    /// root hears about every nested boundary's resolutions.
+    pub fn subscribe(&self, f: impl Fn(ResourceId) + 'static) {
+        self.subscribers.update_value(|subscribers| {
+            subscribers.push(Rc::new(f));
+        });
+    }
+
+    fn notify_resolved(&self, id: ResourceId) {
+        self.subscribers.with_value(|subscribers| {
+            for subscriber in subscribers {
+                subscriber(id);
+            }
+        });
+        if let Some(parent) = self.parent {
+            parent.with_value(|parent| parent.notify_resolved(id));
         }
     }
 
@@ -47,19 +183,133 @@ impl SuspenseContext {
     pub fn increment(&self, id: ResourceId) {
         let setter = self.set_pending_resources;
         let id_setter = self.set_pending_resource_ids;
+        let since_setter = self.set_pending_since;
+        let cancelled = self.cancelled;
         queue_microtask(move || {
+            // the resource may already have been `cancel`led before this microtask ran (e.g. a
+            // route change disposed it while it was still in flight) — honor that instead of
+            // counting it as pending. Leave the marker in `cancelled` for `decrement` to consume
+            // rather than removing it here: `decrement` is always the terminal event for an id,
+            // so it — not whichever of the two happens to drain first — is the one that should
+            // clear the suppression.
+            if cancelled.try_with(|c| c.contains(&id)).unwrap_or(false) {
+                return;
+            }
             setter.update(|n| *n += 1);
             id_setter.update(|ids| {
                 ids.insert(id);
             });
+            since_setter.update(|since| {
+                since.insert(id, Instant::now());
+            });
+        });
+    }
+
+    /// Notifies the suspense context that a new resource is now pending, arming a deadline
+    /// after which [`timed_out`](SuspenseContext::timed_out) flips to `true` if the resource
+    /// (or any other pending resource) still hasn't resolved.
+    ///
+    /// The deadline is armed at most once per pending burst: the first call of a burst arms it
+    /// (even if resources added with plain [`increment`](SuspenseContext::increment) are already
+    /// pending), and later calls before the pending count returns to `0` see the armed flag and
+    /// do not stack timers. The check runs in a microtask queued after `increment`'s, so it sees
+    /// `id` already counted; if that `increment` was suppressed by [`cancel`](SuspenseContext::cancel)
+    /// and nothing else is pending, no deadline is armed.
+    pub fn increment_with_deadline(&self, id: ResourceId, deadline_ms: u32) {
+        self.increment(id);
+        let deadline_armed = self.deadline_armed;
+        let set_deadline_armed = self.set_deadline_armed;
+        let pending_resources = self.pending_resources;
+        let set_timed_out = self.set_timed_out;
+        queue_microtask(move || {
+            let already_armed = deadline_armed.try_with(|armed| *armed).unwrap_or(false);
+            if already_armed {
+                return;
+            }
+            // `increment` was suppressed by a prior `cancel`, so no burst started; arming here
+            // would leave the flag set with nothing left to clear it
+            if pending_resources.try_with(|n| *n == 0).unwrap_or(true) {
+                return;
+            }
+            set_deadline_armed.set(true);
+            set_timeout(
+                move || {
+                    if pending_resources.try_with(|n| *n > 0).unwrap_or(false) {
+                        set_timed_out.set(true);
+                    }
+                },
+                Duration::from_millis(deadline_ms as u64),
+            );
+        });
+    }
+
+    /// Cancels a single resource, e.g. because its owning scope was disposed before it resolved.
+    /// If it's already tracked as pending, this removes it from
+    /// [`pending_resource_ids`](SuspenseContext::pending_resource_ids) and decrements
+    /// [`pending_resources`](SuspenseContext::pending_resources) immediately. Either way, `id` is
+    /// remembered so that a `decrement` which arrives later for it (deferred to a microtask, so
+    /// it can race a `cancel` called in between) is a no-op instead of double-counting or
+    /// resurrecting a cancelled resource.
+    pub fn cancel(&self, id: ResourceId) {
+        let was_pending = self
+            .pending_resource_ids
+            .try_with(|ids| ids.contains(&id))
+            .unwrap_or(false);
+        self.set_cancelled.update(|cancelled| {
+            cancelled.push_back(id);
+            while cancelled.len() > CANCELLED_CAPACITY {
+                cancelled.pop_front();
+            }
+        });
+        if !was_pending {
+            return;
+        }
+        self.set_pending_resource_ids.update(|ids| {
+            ids.remove(&id);
+        });
+        self.set_pending_resources.update(|n| {
+            if *n > 0 {
+                *n -= 1
+            }
         });
+        self.set_pending_since.update(|since| {
+            since.remove(&id);
+        });
+        if self.ready() {
+            self.set_timed_out.set(false);
+            self.set_deadline_armed.set(false);
+        }
+    }
+
+    /// Cancels every currently-pending resource. See [`cancel`](SuspenseContext::cancel).
+    pub fn cancel_all(&self) {
+        let ids = self.pending_resource_ids.get();
+        for id in ids {
+            self.cancel(id);
+        }
     }
 
     /// Notifies the suspense context that a resource has resolved.
     pub fn decrement(&self, id: ResourceId) {
         let setter = self.set_pending_resources;
         let id_setter = self.set_pending_resource_ids;
+        let pending_resources = self.pending_resources;
+        let set_timed_out = self.set_timed_out;
+        let since_setter = self.set_pending_since;
+        let set_recent_resolutions = self.set_recent_resolutions;
+        let cancelled = self.cancelled;
+        let set_cancelled = self.set_cancelled;
+        let set_deadline_armed = self.set_deadline_armed;
+        let this = *self;
         queue_microtask(move || {
+            if cancelled.try_with(|c| c.contains(&id)).unwrap_or(false) {
+                set_cancelled.update(|c| {
+                    if let Some(index) = c.iter().position(|cancelled_id| *cancelled_id == id) {
+                        c.remove(index);
+                    }
+                });
+                return;
+            }
             setter.update(|n| {
                 if *n > 0 {
                     *n -= 1
@@ -68,6 +318,23 @@ impl SuspenseContext {
             id_setter.update(|ids| {
                 ids.remove(&id);
             });
+            since_setter.update(|since| {
+                if let Some(started_at) = since.remove(&id) {
+                    set_recent_resolutions.update(|log| {
+                        log.push_back((id, started_at.elapsed()));
+                        while log.len() > RECENT_RESOLUTIONS_CAPACITY {
+                            log.pop_front();
+                        }
+                    });
+                }
+            });
+            if pending_resources.try_with(|n| *n == 0).unwrap_or(true) {
+                set_timed_out.set(false);
+                set_deadline_armed.set(false);
+            }
+            // fires after the pending set above is updated, so a subscriber that reads
+            // `pending_resource_ids` sees a consistent view
+            this.notify_resolved(id);
         });
     }
 
@@ -77,4 +344,74 @@ impl SuspenseContext {
             .try_with(|n| *n == 0)
             .unwrap_or(false)
     }
+
+    /// Runs `f` exactly once, the first time [`pending_resources`](SuspenseContext::pending_resources)
+    /// reaches `0`. If the context is already ready when this is called, `f` runs immediately
+    /// and synchronously. Resources added after readiness do not cause `f` to run again.
+    pub fn on_ready(&self, f: impl FnOnce() + 'static) {
+        if self.ready() {
+            f();
+            return;
+        }
+        let pending_resources = self.pending_resources;
+        let f = RefCell::new(Some(f));
+        create_effect(self.cx, move |_| {
+            // once `f` has run, stop reading `pending_resources` so this effect no longer
+            // depends on it and is never scheduled again
+            if f.borrow().is_none() {
+                return;
+            }
+            if pending_resources.get() == 0 {
+                if let Some(f) = f.borrow_mut().take() {
+                    f();
+                }
+            }
+        });
+    }
+
+    /// Returns a future that resolves once all resources in this context have loaded, e.g. so
+    /// that server-side rendering can await a boundary before flushing its HTML.
+    pub fn ready_future(&self) -> impl Future<Output = ()> {
+        let state = Rc::new(RefCell::new(ReadyFutureState {
+            ready: self.ready(),
+            waker: None,
+        }));
+        if !state.borrow().ready {
+            let state = Rc::clone(&state);
+            self.on_ready(move || {
+                let waker = {
+                    let mut state = state.borrow_mut();
+                    state.ready = true;
+                    state.waker.take()
+                };
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            });
+        }
+        ReadyFuture { state }
+    }
+}
+
+struct ReadyFutureState {
+    ready: bool,
+    waker: Option<Waker>,
+}
+
+struct ReadyFuture {
+    state: Rc<RefCell<ReadyFutureState>>,
+}
+
+impl Future for ReadyFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.borrow_mut();
+        if state.ready {
+            Poll::Ready(())
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
 }